@@ -1,20 +1,63 @@
-fn init_sieve(arr: &mut [i64], mut i: i64, size: i64) {
-    while i < size {
-        arr[i as usize] = 1;
-        i += 1;
+// The 8 residues mod 30 that are coprime to 2, 3 and 5. Every wheel index
+// maps to exactly one of these within a block of 30 consecutive numbers, so
+// the sieve only ever stores 8/30 of the range instead of 1/2.
+const WHEEL: [i64; 8] = [1, 7, 11, 13, 17, 19, 23, 29];
+
+// WHEEL_POS[n % 30] gives the position of residue n in WHEEL, or -1 if n is
+// a multiple of 2, 3 or 5 and so has no wheel slot at all.
+const WHEEL_POS: [i8; 30] = [
+    -1, 0, -1, -1, -1, -1, -1, 1, -1, -1, -1, 2, -1, 3, -1, -1, -1, 4, -1, 5, -1, -1, -1, 6, -1,
+    -1, -1, -1, -1, 7,
+];
+
+// 2, 3 and 5 are themselves prime but fall outside the wheel, so every
+// prime-counting function has to special-case them in.
+const SMALL_PRIMES: [i64; 3] = [2, 3, 5];
+
+fn wheel_index(n: i64) -> Option<i64> {
+    let pos = WHEEL_POS[(n % 30) as usize];
+    if pos < 0 {
+        return None;
+    }
+    Some((n / 30) * 8 + pos as i64)
+}
+
+fn wheel_value(i: i64) -> i64 {
+    (i / 8) * 30 + WHEEL[(i % 8) as usize]
+}
+
+// Number of wheel indices whose value is <= limit.
+fn wheel_size(limit: i64) -> i64 {
+    if limit < 1 {
+        return 0;
     }
+    let block = limit / 30;
+    let rem = limit % 30;
+    let extra = WHEEL.iter().filter(|&&r| r <= rem).count() as i64;
+    block * 8 + extra
 }
 
-fn clear_multiples(arr: &mut [i64], factor: i64, mut num: i64, size: i64) {
-    while num < size {
-        arr[num as usize] = 0;
-        num += factor;
+fn init_sieve(words: &mut [u32]) {
+    words[0] |= 1;
+}
+
+fn clear_multiples(words: &mut [u32], factor: i64, limit: i64) {
+    let mut j = wheel_index(factor).expect("factor must be a wheel-eligible prime");
+    loop {
+        let k = wheel_value(j);
+        let composite = factor * k;
+        if composite > limit {
+            break;
+        }
+        let idx = wheel_index(composite).expect("product of two wheel numbers is wheel-eligible");
+        words[(idx >> 5) as usize] |= 1u32 << (idx & 31);
+        j += 1;
     }
 }
 
-fn find_next_prime(arr: &[i64], mut i: i64, limit: i64) -> i64 {
+fn find_next_prime(words: &[u32], mut i: i64, limit: i64) -> i64 {
     while i <= limit {
-        if arr[i as usize] == 1 {
+        if (words[(i >> 5) as usize] & (1u32 << (i & 31))) == 0 {
             return i;
         }
         i += 1;
@@ -22,7 +65,11 @@ fn find_next_prime(arr: &[i64], mut i: i64, limit: i64) -> i64 {
     0
 }
 
-fn isqrt(n: i64, mut guess: i64) -> i64 {
+fn isqrt(n: i64, guess: i64) -> i64 {
+    if n == 0 {
+        return 0;
+    }
+    let mut guess = guess.max(1);
     loop {
         let next = (guess + n / guess) / 2;
         if next >= guess {
@@ -32,11 +79,16 @@ fn isqrt(n: i64, mut guess: i64) -> i64 {
     }
 }
 
-fn count_primes(arr: &[i64], size: i64) -> i64 {
-    let mut count: i64 = 1;
-    let mut i: i64 = 0;
-    while i < size {
-        if arr[i as usize] == 1 {
+fn count_window(words: &[u32], size: i64) -> i64 {
+    let full_words = (size / 32) as usize;
+    let mut count: i64 = 0;
+    for word in &words[..full_words] {
+        count += word.count_zeros() as i64;
+    }
+    let remaining = size - (full_words as i64) * 32;
+    let mut i = full_words as i64 * 32;
+    while i < full_words as i64 * 32 + remaining {
+        if (words[(i >> 5) as usize] & (1u32 << (i & 31))) == 0 {
             count += 1;
         }
         i += 1;
@@ -44,30 +96,223 @@ fn count_primes(arr: &[i64], size: i64) -> i64 {
     count
 }
 
-fn run_sieve(limit: i64) -> i64 {
-    let size = (limit + 1) / 2;
-    let mut arr = vec![0i64; size as usize];
-    init_sieve(&mut arr, 0, size);
-    arr[0] = 0;
+fn count_primes(words: &[u32], size: i64, limit: i64) -> i64 {
+    let mut count = count_window(words, size);
+    for &p in &SMALL_PRIMES {
+        if p <= limit {
+            count += 1;
+        }
+    }
+    count
+}
+
+fn sieve_up_to(limit: i64) -> (Vec<u32>, i64) {
+    let size = wheel_size(limit);
+    let word_count = (size + 31) / 32;
+    let mut words = vec![0u32; word_count as usize];
+    if size > 0 {
+        init_sieve(&mut words);
+    }
 
     let q = isqrt(limit, limit / 2);
+    let q_idx = wheel_size(q) - 1;
+
+    let mut idx: i64 = 1;
+    while idx <= q_idx {
+        let next = find_next_prime(&words, idx, q_idx);
+        if next == 0 {
+            break;
+        }
+        let factor = wheel_value(next);
+        clear_multiples(&mut words, factor, limit);
+        idx = next + 1;
+    }
+
+    (words, size)
+}
+
+// Number of primes <= limit.
+fn prime_pi(limit: i64) -> i64 {
+    let (words, size) = sieve_up_to(limit);
+    count_primes(&words, size, limit)
+}
+
+// The nth prime (1-indexed: nth_prime(1) == 2), found by sieving up to the
+// analytic upper bound for p_n and scanning until the nth one turns up.
+fn nth_prime(n: i64) -> i64 {
+    assert!(n >= 1, "nth_prime is 1-indexed: n must be >= 1, got {}", n);
+    let limit = if n >= 6 {
+        let nf = n as f64;
+        (nf * nf.ln() + nf * nf.ln().ln()).ceil() as i64
+    } else {
+        13
+    };
+    Primes::new(limit)
+        .nth((n - 1) as usize)
+        .expect("analytic upper bound should always contain the nth prime")
+}
+
+// Tests whether `n` is prime against an already-computed `sieve_up_to`
+// result, so one sieve can be reused across many membership tests instead
+// of recomputing the whole array per query.
+fn is_prime(n: i64, sieve: &(Vec<u32>, i64)) -> bool {
+    if SMALL_PRIMES.contains(&n) {
+        return true;
+    }
+    if n < 2 || n % 2 == 0 || n % 3 == 0 || n % 5 == 0 {
+        return false;
+    }
 
-    let mut factor: i64 = 3;
-    while factor <= q {
-        let next = find_next_prime(&arr, factor / 2, q / 2);
+    let (words, size) = sieve;
+    let idx = wheel_index(n).expect("n coprime to 2, 3 and 5 is always wheel-eligible");
+    if idx >= *size {
+        return false;
+    }
+    (words[(idx >> 5) as usize] & (1u32 << (idx & 31))) == 0
+}
+
+// Base primes up to `limit`, as actual values (7, 11, 13, ...), read off a
+// fully sieved wheel bit array. 2, 3 and 5 fall outside the wheel and are
+// never needed here: the wheel already excludes their multiples.
+fn base_primes(limit: i64) -> Vec<i64> {
+    let (words, size) = sieve_up_to(limit);
+    let mut primes = Vec::new();
+    let mut i = 1;
+    while i < size {
+        let next = find_next_prime(&words, i, size - 1);
         if next == 0 {
             break;
         }
-        let actual_factor = next * 2 + 1;
-        let start = actual_factor * actual_factor / 2;
-        clear_multiples(&mut arr, actual_factor, start, size);
-        factor = actual_factor + 2;
+        primes.push(wheel_value(next));
+        i = next + 1;
+    }
+    primes
+}
+
+// Number of wheel-index slots held in memory at once by the segmented
+// sieve: 32K words of 32 bits each.
+const SEGMENT_WORDS: i64 = 32 * 1024;
+const SEGMENT_SIZE: i64 = SEGMENT_WORDS * 32;
+
+// Segmented sieve: bounds memory to the base primes up to sqrt(limit) plus
+// one fixed-size window, so `limit` can be arbitrarily large without one
+// giant allocation.
+fn segmented_sieve(limit: i64) -> i64 {
+    let size = wheel_size(limit);
+    let q = isqrt(limit, limit / 2);
+    let primes = base_primes(q);
+
+    // For each base prime, the wheel index of the next cofactor `k` such
+    // that `p * k` is the next composite to cross off. Carried across
+    // windows so no multiple is ever recomputed or repeated.
+    let mut next_k_idx: Vec<i64> = primes
+        .iter()
+        .map(|&p| wheel_index(p).expect("base primes are wheel-eligible"))
+        .collect();
+
+    let mut count: i64 = 0;
+    for &p in &SMALL_PRIMES {
+        if p <= limit {
+            count += 1;
+        }
+    }
+
+    let mut low: i64 = 0;
+    while low < size {
+        let high = (low + SEGMENT_SIZE).min(size);
+        let window_size = high - low;
+        let mut window = vec![0u32; ((window_size + 31) / 32) as usize];
+        if low == 0 {
+            window[0] |= 1; // 1 is not prime
+        }
+
+        for (i, &p) in primes.iter().enumerate() {
+            let mut kj = next_k_idx[i];
+            loop {
+                let k = wheel_value(kj);
+                let composite = p * k;
+                if composite > limit {
+                    break;
+                }
+                let idx = wheel_index(composite).expect("product of two wheel numbers is wheel-eligible");
+                if idx >= high {
+                    break;
+                }
+                if idx >= low {
+                    let rel = idx - low;
+                    window[(rel >> 5) as usize] |= 1u32 << (rel & 31);
+                }
+                kj += 1;
+            }
+            next_k_idx[i] = kj;
+        }
+
+        count += count_window(&window, window_size);
+        low = high;
     }
 
-    count_primes(&arr, size)
+    count
+}
+
+// Lazily walks a sieved bit array, yielding each prime on demand instead of
+// requiring callers to go through `count_primes`.
+struct Primes {
+    words: Vec<u32>,
+    size: i64,
+    cursor: i64,
+    limit: i64,
+    small_idx: usize,
+}
+
+impl Primes {
+    fn new(limit: i64) -> Primes {
+        let (words, size) = sieve_up_to(limit);
+        Primes {
+            words,
+            size,
+            cursor: 1,
+            limit,
+            small_idx: 0,
+        }
+    }
+}
+
+impl Iterator for Primes {
+    type Item = i64;
+
+    fn next(&mut self) -> Option<i64> {
+        while self.small_idx < SMALL_PRIMES.len() {
+            let p = SMALL_PRIMES[self.small_idx];
+            self.small_idx += 1;
+            if p <= self.limit {
+                return Some(p);
+            }
+        }
+
+        let next = find_next_prime(&self.words, self.cursor, self.size - 1);
+        if next == 0 {
+            return None;
+        }
+        self.cursor = next + 1;
+        Some(wheel_value(next))
+    }
 }
 
 fn main() {
-    let count = run_sieve(1000000);
+    let count = prime_pi(1000000);
     println!("Primes found: {}", count);
+
+    let segmented_count = segmented_sieve(1000000);
+    println!("Primes found (segmented): {}", segmented_count);
+
+    let first_ten: Vec<i64> = Primes::new(1000000).take(10).collect();
+    println!("First ten primes: {:?}", first_ten);
+
+    println!("pi(1000000) = {}", prime_pi(1000000));
+    println!("1000th prime = {}", nth_prime(1000));
+
+    let sieve = sieve_up_to(100);
+    for n in [2, 9, 17, 100] {
+        println!("is_prime({}) = {}", n, is_prime(n, &sieve));
+    }
 }